@@ -6,35 +6,345 @@ use pyo3::IntoPyPointer;
 use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use smallvec::SmallVec;
 use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::marker::PhantomData;
+use std::rc::Rc;
+
+// Key strings (typical of homogeneous API-response records) repeat across
+// every object in a payload; reuse a single PyString per distinct key below
+// this length instead of allocating one per occurrence.
+const KEY_CACHE_MAX_LEN: usize = 64;
 
 import_exception!(json, JSONDecodeError);
 
 pub fn deserialize(py: Python, data: &str) -> PyResult<PyObject> {
-    let seed = JsonValue::new(py);
+    let seed = JsonValue::new(py, data.as_bytes());
+    let key_cache = Rc::clone(&seed.key_cache);
+    let duplicate_error = Rc::clone(&seed.duplicate_error);
     let mut deserializer = serde_json::Deserializer::from_str(data);
-    match seed.deserialize(&mut deserializer) {
-        Ok(py_ptr) => {
-            deserializer
-                .end()
-                .map_err(|e| JSONDecodeError::py_err((e.to_string(), "", 0)))?;
-            Ok(unsafe { PyObject::from_owned_ptr(py, py_ptr) })
+    let result = match seed.deserialize(&mut deserializer) {
+        Ok(py_ptr) => deserializer
+            .end()
+            .map_err(|e| json_decode_error(data, &e))
+            .map(|_| unsafe { PyObject::from_owned_ptr(py, py_ptr) }),
+        Err(e) => Err(take_duplicate_error(&duplicate_error, data)
+            .unwrap_or_else(|| json_decode_error(data, &e))),
+    };
+    release_key_cache(&key_cache);
+    result
+}
+
+// A `DuplicateKeyPolicy::Raise` error computes its own exact position at the
+// point the duplicate is found (see `visit_map`); by the time the `Err`
+// reaches here, serde_json's own cursor has moved on to wherever parsing
+// stopped, so `e.line()`/`e.column()` no longer describe the duplicate.
+// Prefer this precomputed position over `json_decode_error`'s whenever set.
+fn take_duplicate_error(
+    duplicate_error: &Rc<RefCell<Option<(String, usize)>>>,
+    doc: &str,
+) -> Option<PyErr> {
+    duplicate_error
+        .borrow_mut()
+        .take()
+        .map(|(msg, pos)| JSONDecodeError::py_err((msg, doc.to_string(), pos)))
+}
+
+// `cached_key` holds one extra reference per distinct cached key so it can
+// be reused across nested objects; release those once the top-level call
+// is done, or every distinct key name leaks for the life of the process.
+fn release_key_cache(cache: &Rc<RefCell<HashMap<String, *mut pyo3::ffi::PyObject>>>) {
+    for (_, ptr) in cache.borrow_mut().drain() {
+        unsafe { pyo3::ffi::Py_DECREF(ptr) };
+    }
+}
+
+// serde_json's line/column are counted over raw bytes, but `JSONDecodeError`
+// indexes `doc` (a Python str) by character, so a byte offset has to be
+// translated into a char offset rather than used as one directly.
+fn position_of(data: &str, line: usize, column: usize) -> usize {
+    let mut byte_pos = byte_offset_of(data.as_bytes(), line, column);
+    while byte_pos > 0 && !data.is_char_boundary(byte_pos) {
+        byte_pos -= 1;
+    }
+    data[..byte_pos].chars().count()
+}
+
+// Byte offset of (line, column) in `data`, using serde_json's own convention
+// of counting columns as a byte distance from the last newline.
+fn byte_offset_of(data: &[u8], line: usize, column: usize) -> usize {
+    let mut line_start = 0;
+    let mut current_line = 1;
+    if line > 1 {
+        for (idx, &b) in data.iter().enumerate() {
+            if b == b'\n' {
+                current_line += 1;
+                if current_line == line {
+                    line_start = idx + 1;
+                    break;
+                }
+            }
+        }
+    }
+    (line_start + column.saturating_sub(1)).min(data.len())
+}
+
+fn json_decode_error(data: &str, e: &serde_json::Error) -> PyErr {
+    let pos = position_of(data, e.line(), e.column());
+    JSONDecodeError::py_err((e.to_string(), data, pos))
+}
+
+// Byte-based (line, column) for `byte_offset`, matching serde_json's own
+// convention of counting columns as a byte distance from the last newline.
+fn line_col_at(data: &[u8], byte_offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for &b in &data[..byte_offset.min(data.len())] {
+        if b == b'\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
         }
-        Err(e) => {
-            return Err(JSONDecodeError::py_err((e.to_string(), "", 0)));
+    }
+    (line, column)
+}
+
+// Finds the first occurrence of `"key"` at or after `start` that is followed
+// (ignoring whitespace) by a `:`, i.e. an actual object key rather than a
+// same-text string value elsewhere in the document. Used only to recover a
+// duplicate key's real position for error reporting; `start` lets callers
+// resume from the last key found instead of rescanning from the top, which
+// both keeps this roughly linear and avoids matching a key-shaped occurrence
+// that comes later in the document than the one actually being looked for.
+fn find_key_occurrence(data: &[u8], start: usize, key: &str) -> Option<usize> {
+    let mut needle = Vec::with_capacity(key.len() + 2);
+    needle.push(b'"');
+    needle.extend_from_slice(key.as_bytes());
+    needle.push(b'"');
+
+    let mut i = start;
+    while i + needle.len() <= data.len() {
+        if &data[i..i + needle.len()] == needle.as_slice() {
+            let mut after = i + needle.len();
+            while after < data.len() && data[after].is_ascii_whitespace() {
+                after += 1;
+            }
+            if after < data.len() && data[after] == b':' {
+                return Some(i);
+            }
         }
+        i += 1;
     }
+    None
+}
+
+// Converts a byte offset in `data` to the character offset `JSONDecodeError`
+// expects. Counts an invalid byte the same way `String::from_utf8_lossy`
+// does (as a single replacement character), so it stays correct even when
+// `data` isn't valid UTF-8 and `doc` ends up being a lossy re-encoding of it.
+fn char_offset_of_byte(data: &[u8], byte_offset: usize) -> usize {
+    let byte_offset = byte_offset.min(data.len());
+    String::from_utf8_lossy(&data[..byte_offset]).chars().count()
+}
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16_BE_BOM: [u8; 2] = [0xFE, 0xFF];
+const UTF32_LE_BOM: [u8; 4] = [0xFF, 0xFE, 0x00, 0x00];
+const UTF32_BE_BOM: [u8; 4] = [0x00, 0x00, 0xFE, 0xFF];
+
+// Strips a leading UTF-8 BOM (serde_json treats it as invalid whitespace) and
+// rejects UTF-16/UTF-32 BOMs outright, since those encodings aren't supported.
+// Every position reported while parsing the returned slice (and hence any
+// JSONDecodeError raised for it) is relative to *this* stripped buffer, not
+// the original input the caller passed in.
+fn strip_bom(data: &[u8]) -> PyResult<&[u8]> {
+    if data.starts_with(&UTF32_LE_BOM) || data.starts_with(&UTF32_BE_BOM) {
+        return Err(JSONDecodeError::py_err((
+            "UTF-32 byte order mark is not supported, expected UTF-8".to_string(),
+            "",
+            0,
+        )));
+    }
+    if data.starts_with(&UTF16_LE_BOM) || data.starts_with(&UTF16_BE_BOM) {
+        return Err(JSONDecodeError::py_err((
+            "UTF-16 byte order mark is not supported, expected UTF-8".to_string(),
+            "",
+            0,
+        )));
+    }
+    if data.starts_with(&UTF8_BOM) {
+        return Ok(&data[UTF8_BOM.len()..]);
+    }
+    Ok(data)
+}
+
+// Entry point for bytes/bytearray/memoryview input: parses directly out of
+// the byte slice (serde_json validates UTF-8 as it goes) instead of forcing
+// callers to decode to `str` first.
+pub fn deserialize_from_bytes(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let data = strip_bom(data)?;
+    let seed = JsonValue::new(py, data);
+    let key_cache = Rc::clone(&seed.key_cache);
+    let mut deserializer = serde_json::Deserializer::from_slice(data);
+    let result = match seed.deserialize(&mut deserializer) {
+        Ok(py_ptr) => deserializer
+            .end()
+            .map_err(|e| json_decode_error_bytes(data, &e))
+            .map(|_| unsafe { PyObject::from_owned_ptr(py, py_ptr) }),
+        Err(e) => Err(json_decode_error_bytes(data, &e)),
+    };
+    release_key_cache(&key_cache);
+    result
+}
+
+// Unlike json_decode_error, this operates on the *original* byte slice:
+// e.line()/e.column() are counted over those raw bytes by serde_json, so the
+// byte offset has to be located before any lossy re-encoding happens. Only
+// the final char count needs to account for how String::from_utf8_lossy will
+// later turn invalid bytes into replacement characters when building `doc` --
+// doing the whole position calculation on the already-lossy string (as
+// before) desyncs it from serde_json's byte-based count whenever the input
+// contains invalid UTF-8.
+fn json_decode_error_bytes(data: &[u8], e: &serde_json::Error) -> PyErr {
+    let byte_offset = byte_offset_of(data, e.line(), e.column());
+    let pos = char_offset_of_byte(data, byte_offset);
+    JSONDecodeError::py_err((e.to_string(), String::from_utf8_lossy(data).into_owned(), pos))
+}
+
+// With the "arbitrary_precision" Cargo feature enabled (see Cargo.toml),
+// serde_json routes every number that doesn't fit its i64/u64/f64 fast path
+// to the visitor as a single-entry map under this private key, with the
+// original decimal text (sign included) as the value. That covers both
+// integers too large for i64/u64 and ordinary floats, so the text has to be
+// classified before deciding how to build the Python object.
+const ARBITRARY_PRECISION_KEY: &str = "$serde_json::private::Number";
+
+fn is_integer_literal(digits: &str) -> bool {
+    !digits.contains('.') && !digits.contains('e') && !digits.contains('E')
+}
+
+fn number_from_digits(py: Python, digits: &str) -> *mut pyo3::ffi::PyObject {
+    if is_integer_literal(digits) {
+        let cstr = std::ffi::CString::new(digits).expect("JSON number digits contain no NUL byte");
+        unsafe { pyo3::ffi::PyLong_FromString(cstr.as_ptr(), std::ptr::null_mut(), 10) }
+    } else {
+        let value: f64 = digits.parse().unwrap_or(f64::NAN);
+        PyFloat::new(py, value).into_ptr()
+    }
+}
+
+/// What to do when an object contains the same key more than once.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Keep the last value seen for a key (the current, `json`-compatible behavior).
+    Last,
+    /// Keep the first value seen for a key, ignoring later duplicates.
+    First,
+    /// Raise `JSONDecodeError` if a key repeats within the same object.
+    Raise,
+}
+
+impl Default for DuplicateKeyPolicy {
+    fn default() -> Self {
+        DuplicateKeyPolicy::Last
+    }
+}
+
+/// Tunables for [`deserialize_with_options`]. Defaults match plain
+/// [`deserialize`]: no keys are passed through as raw JSON, and duplicate
+/// keys silently resolve to the last value.
+#[derive(Default)]
+pub struct DecodeOptions {
+    /// Keys whose value should be handed back verbatim (as the original
+    /// JSON text) instead of being parsed into Python objects.
+    pub raw_keys: HashSet<String>,
+    /// How to resolve an object that repeats the same key.
+    pub duplicate_keys: DuplicateKeyPolicy,
+}
+
+pub fn deserialize_with_options(py: Python, data: &str, options: &DecodeOptions) -> PyResult<PyObject> {
+    let seed = JsonValue::with_options(py, data.as_bytes(), options);
+    let key_cache = Rc::clone(&seed.key_cache);
+    let duplicate_error = Rc::clone(&seed.duplicate_error);
+    let mut deserializer = serde_json::Deserializer::from_str(data);
+    let result = match seed.deserialize(&mut deserializer) {
+        Ok(py_ptr) => deserializer
+            .end()
+            .map_err(|e| json_decode_error(data, &e))
+            .map(|_| unsafe { PyObject::from_owned_ptr(py, py_ptr) }),
+        Err(e) => Err(take_duplicate_error(&duplicate_error, data)
+            .unwrap_or_else(|| json_decode_error(data, &e))),
+    };
+    release_key_cache(&key_cache);
+    result
 }
 
 #[derive(Clone)]
 struct JsonValue<'a> {
     py: Python<'a>,
+    // Backing document, kept around only so a `DuplicateKeyPolicy::Raise`
+    // error can locate the offending key's real position.
+    data: &'a [u8],
+    key_cache: Rc<RefCell<HashMap<String, *mut pyo3::ffi::PyObject>>>,
+    raw_keys: Rc<HashSet<String>>,
+    duplicate_keys: DuplicateKeyPolicy,
+    // Byte offset to resume searching from when locating the next key's
+    // position in `data` (see `find_key_occurrence`). Monotonic because
+    // serde_json visits keys in document order, so each search can pick up
+    // right where the previous one left off instead of rescanning from 0.
+    key_cursor: Rc<Cell<usize>>,
+    // Set by `visit_map` the moment a `DuplicateKeyPolicy::Raise` duplicate
+    // is found, since that's the only point with the real position; by the
+    // time the resulting `Err` reaches the top-level `deserialize*` caller,
+    // serde_json's own line/column no longer point at the duplicate.
+    duplicate_error: Rc<RefCell<Option<(String, usize)>>>,
 }
 
 impl<'a> JsonValue<'a> {
-    fn new(py: Python<'a>) -> JsonValue<'a> {
-        JsonValue { py }
+    fn new(py: Python<'a>, data: &'a [u8]) -> JsonValue<'a> {
+        JsonValue {
+            py,
+            data,
+            key_cache: Rc::new(RefCell::new(HashMap::new())),
+            raw_keys: Rc::new(HashSet::new()),
+            duplicate_keys: DuplicateKeyPolicy::Last,
+            key_cursor: Rc::new(Cell::new(0)),
+            duplicate_error: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    fn with_options(py: Python<'a>, data: &'a [u8], options: &DecodeOptions) -> JsonValue<'a> {
+        JsonValue {
+            py,
+            data,
+            key_cache: Rc::new(RefCell::new(HashMap::new())),
+            raw_keys: Rc::new(options.raw_keys.clone()),
+            duplicate_keys: options.duplicate_keys,
+            key_cursor: Rc::new(Cell::new(0)),
+            duplicate_error: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    // Returns a new reference to the PyString for `key`, on a cache hit as
+    // well as on a miss; the cache keeps its own separate reference. The
+    // caller owns the returned reference and must release it once done
+    // (`PyDict_SetItem` takes a reference of its own rather than stealing).
+    fn cached_key(&self, key: &str) -> *mut pyo3::ffi::PyObject {
+        if key.len() >= KEY_CACHE_MAX_LEN {
+            return PyString::new(self.py, key).into_ptr();
+        }
+        let mut cache = self.key_cache.borrow_mut();
+        if let Some(&ptr) = cache.get(key) {
+            unsafe { pyo3::ffi::Py_INCREF(ptr) };
+            return ptr;
+        }
+        let ptr = PyString::new(self.py, key).into_ptr();
+        unsafe { pyo3::ffi::Py_INCREF(ptr) };
+        cache.insert(key.to_owned(), ptr);
+        ptr
     }
 }
 
@@ -122,12 +432,81 @@ impl<'de, 'a> Visitor<'de> for JsonValue<'a> {
         A: MapAccess<'de>,
     {
         let dict_ptr = PyDict::new(self.py).into_ptr();
-        while let Some((key, value)) = map.next_entry_seed(PhantomData::<Cow<str>>, self.clone())? {
-            let _ = unsafe { pyo3::ffi::PyDict_SetItem(
-                dict_ptr,
-                PyString::new(self.py, &key).into_ptr(),
-                value,
-            ) };
+        let mut first = true;
+        while let Some(key) = map.next_key_seed(PhantomData::<Cow<str>>)? {
+            if first && key == ARBITRARY_PRECISION_KEY {
+                let digits: Cow<str> = map.next_value()?;
+                unsafe { pyo3::ffi::Py_DECREF(dict_ptr) };
+                return Ok(number_from_digits(self.py, &digits));
+            }
+            first = false;
+
+            // Record this key's real position in `data`, in document order,
+            // before consuming its value. A plain whole-document search for
+            // `"key"` (the previous approach) could match an unrelated
+            // earlier occurrence of the same text as a string *value*;
+            // resuming from the last found key and requiring a following
+            // `:` keeps this anchored to actual object keys.
+            let key_offset = find_key_occurrence(self.data, self.key_cursor.get(), &key);
+            if let Some(offset) = key_offset {
+                self.key_cursor.set(offset + key.len() + 2);
+            }
+
+            let value = if self.raw_keys.contains(key.as_ref()) {
+                let raw: Box<serde_json::value::RawValue> = map.next_value()?;
+                PyString::new(self.py, raw.get()).into_ptr()
+            } else {
+                map.next_value_seed(self.clone())?
+            };
+            let key_ptr = self.cached_key(&key);
+            match self.duplicate_keys {
+                DuplicateKeyPolicy::Last => {
+                    let _ = unsafe { pyo3::ffi::PyDict_SetItem(dict_ptr, key_ptr, value) };
+                    unsafe { pyo3::ffi::Py_DECREF(key_ptr) };
+                }
+                DuplicateKeyPolicy::First => {
+                    let exists = unsafe { pyo3::ffi::PyDict_Contains(dict_ptr, key_ptr) } == 1;
+                    if exists {
+                        unsafe {
+                            pyo3::ffi::Py_DECREF(key_ptr);
+                            pyo3::ffi::Py_DECREF(value);
+                        }
+                    } else {
+                        let _ = unsafe { pyo3::ffi::PyDict_SetItem(dict_ptr, key_ptr, value) };
+                        unsafe { pyo3::ffi::Py_DECREF(key_ptr) };
+                    }
+                }
+                DuplicateKeyPolicy::Raise => {
+                    let exists = unsafe { pyo3::ffi::PyDict_Contains(dict_ptr, key_ptr) } == 1;
+                    if exists {
+                        unsafe {
+                            pyo3::ffi::Py_DECREF(dict_ptr);
+                            pyo3::ffi::Py_DECREF(key_ptr);
+                            pyo3::ffi::Py_DECREF(value);
+                        }
+                        let msg = match key_offset {
+                            Some(byte_offset) => {
+                                let (line, column) = line_col_at(self.data, byte_offset);
+                                let message = format!(
+                                    "duplicate key: {:?} at line {} column {}",
+                                    key, line, column
+                                );
+                                // Stash the exact position now, while we still
+                                // have it; see `duplicate_error`'s doc comment.
+                                *self.duplicate_error.borrow_mut() = Some((
+                                    message.clone(),
+                                    char_offset_of_byte(self.data, byte_offset),
+                                ));
+                                message
+                            }
+                            None => format!("duplicate key: {:?}", key),
+                        };
+                        return Err(de::Error::custom(msg));
+                    }
+                    let _ = unsafe { pyo3::ffi::PyDict_SetItem(dict_ptr, key_ptr, value) };
+                    unsafe { pyo3::ffi::Py_DECREF(key_ptr) };
+                }
+            }
         }
         Ok(dict_ptr)
     }